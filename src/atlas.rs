@@ -0,0 +1,66 @@
+use image::RgbaImage;
+use tetra::graphics::{Rectangle, Texture};
+use tetra::Context;
+
+const ATLAS_WIDTH: u32 = 2048;
+const SHELF_HEIGHT_TOLERANCE: u32 = 4;
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    cursor_x: u32,
+}
+
+pub struct Atlas {
+    buffer: RgbaImage,
+    shelves: Vec<Shelf>,
+    cursor_y: u32,
+}
+
+impl Atlas {
+    pub fn new() -> Self {
+        Self {
+            buffer: RgbaImage::new(ATLAS_WIDTH, ATLAS_WIDTH),
+            shelves: Vec::new(),
+            cursor_y: 0,
+        }
+    }
+
+    pub fn insert(&mut self, image: &RgbaImage) -> Rectangle {
+        let (width, height) = image.dimensions();
+        assert!(
+            width <= ATLAS_WIDTH && height <= ATLAS_WIDTH,
+            "image {}x{} does not fit in a {}x{} atlas", width, height, ATLAS_WIDTH, ATLAS_WIDTH,
+        );
+
+        let shelf_index = self.shelves.iter().position(|shelf| {
+            height <= shelf.height
+                && shelf.height - height <= SHELF_HEIGHT_TOLERANCE
+                && ATLAS_WIDTH - shelf.cursor_x >= width
+        });
+
+        let shelf_index = shelf_index.unwrap_or_else(|| {
+            assert!(
+                ATLAS_WIDTH - self.cursor_y >= height,
+                "atlas out of vertical space: {}x{} shelf does not fit below y={} in a {}x{} atlas",
+                width, height, self.cursor_y, ATLAS_WIDTH, ATLAS_WIDTH,
+            );
+            let shelf = Shelf { y: self.cursor_y, height, cursor_x: 0 };
+            self.cursor_y += height;
+            self.shelves.push(shelf);
+            self.shelves.len() - 1
+        });
+
+        let shelf = &mut self.shelves[shelf_index];
+        let (x, y) = (shelf.cursor_x, shelf.y);
+        shelf.cursor_x += width;
+
+        image::imageops::overlay(&mut self.buffer, image, x.into(), y.into());
+
+        Rectangle::new(x as f32, y as f32, width as f32, height as f32)
+    }
+
+    pub fn build(self, ctx: &mut Context) -> tetra::Result<Texture> {
+        Texture::from_rgba(ctx, ATLAS_WIDTH as i32, ATLAS_WIDTH as i32, &self.buffer)
+    }
+}
@@ -1,130 +1,17 @@
-use tetra::graphics::{self, Color, DrawParams, Texture, Rectangle, Camera};
-use tetra::math::Vec2;
+use tetra::graphics::{self, Color, Camera};
+use tetra::input::{self, MouseButton};
+use tetra::math::{Vec2, Vec4};
 use tetra::{Context, ContextBuilder, State, Event};
-use std::collections::HashMap;
-use serde::{Serialize, Deserialize};
-
-use std::fs::File;
-use std::io::Read;
-
-#[derive(Serialize, Deserialize)]
-struct MapData {
-    image: String,
-    tiles: HashMap<i32, TileData>,
-    width: usize,
-    height: usize,
-    map: [[i32; 6]; 6],
-}
-
-#[derive(Serialize, Deserialize)]
-#[serde(remote = "Rectangle")]
-struct RectangleDef {
-    x: f32,
-    y: f32,
-    width: f32,
-    height: f32,
-}
-
-#[derive(Serialize, Deserialize)]
-struct Point {
-    x: i32,
-    y: i32,
-}
-
-#[derive(Serialize, Deserialize)]
-struct TileData {
-    #[serde(with = "RectangleDef")]
-    clip: Rectangle,
-    origin: Point,
-}
-
-struct Map {    
-    tiles: HashMap<i32, Tile>,
-    map: [[i32; 6]; 6],
-}
-
-impl Map {
-    fn from_json(ctx: &mut Context, filename: &str) -> Self {
-        let map_json = read_file(filename);
-        let map_data: MapData = serde_json::from_str(&map_json).unwrap();
-        let texture = Texture::new(ctx, map_data.image).unwrap();
-        
-        let mut tiles = HashMap::new();
-        tiles.insert(0, Tile {
-            texture: texture.clone(),
-            clip: Rectangle::new(0.0, 0.0, 64.0, 64.0),
-            origin: Vec2::new(0.0, 0.0),
-        });
-        tiles.insert(1, Tile {
-            texture: texture.clone(),
-            clip: Rectangle::new(7.0 * ISO_WIDTH, 3.0 * ISO_HEIGHT, 64.0, 64.0),
-            origin: Vec2::new(0.0, 0.0),
-        });
-        tiles.insert(2, Tile {
-            texture: texture.clone(),
-            clip: Rectangle::new(0.0, 0.0, 64.0, 64.0),
-            origin: Vec2::new(0.0, 0.0),
-        });
-        tiles.insert(3, Tile {
-            texture: texture.clone(),
-            clip: Rectangle::new(8.0 * ISO_WIDTH, 3.0 * ISO_HEIGHT, 64.0, 64.0),
-            origin: Vec2::new(0.0, 0.0),
-        });
-        tiles.insert(4, Tile {
-            texture: texture.clone(),
-            clip: Rectangle::new(0.0, 0.0, 64.0, 64.0),
-            origin: Vec2::new(0.0, 0.0),
-        });
-        tiles.insert(5, Tile {
-            texture: texture.clone(),
-            clip: Rectangle::new(0.0, 0.0, 64.0, 64.0),
-            origin: Vec2::new(0.0, 0.0),
-        });
-        tiles.insert(6, Tile {
-            texture: texture.clone(),
-            clip: Rectangle::new(0.0, 0.0, 64.0, 64.0),
-            origin: Vec2::new(0.0, 0.0),
-        });
-        Self {
-            map: map_data.map,
-            tiles,
-        }
-    }
-}
-
-pub fn read_file(filepath: &str) -> String {
-    let mut file = File::open(filepath)
-        .expect("could not open file");
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).unwrap();
-
-    contents
-}
-
-const ISO_WIDTH: f32 = 64.0;
-const ISO_HEIGHT: f32 = 64.0;
+use std::env;
 
-struct Tile {
-    texture: Texture,
-    clip: Rectangle,
-    origin: Vec2<f32>,
-}
+mod atlas;
+mod map;
+use map::{Map, Tile};
 
-impl Tile {
-    fn draw(&self, ctx: &mut Context, x: i32, y: i32) {
-        let position = cartesian_to_isometric(Vec2::new(x,y));
-        graphics::draw(
-            ctx,
-            &self.texture,
-            DrawParams::new()
-                .position(position)
-                .origin(self.origin)
-                .clip(self.clip),
-        );
-    }
-}
+pub(crate) const ISO_WIDTH: f32 = 64.0;
+pub(crate) const ISO_HEIGHT: f32 = 64.0;
 
-fn cartesian_to_isometric(cartesian_position: Vec2<i32>) -> Vec2<f32> {
+pub(crate) fn cartesian_to_isometric(cartesian_position: Vec2<i32>) -> Vec2<f32> {
     Vec2::new(
         (cartesian_position.x - cartesian_position.y) as f32,
         (cartesian_position.x + cartesian_position.y) as f32 / 2.0
@@ -138,9 +25,17 @@ fn isometric_to_cartesian(isometric_position: Vec2<f32>) -> Vec2<i32> {
     )
 }
 
+struct Drawable<'a> {
+    tile: &'a Tile,
+    x: i32,
+    y: i32,
+    depth: i32,
+}
+
 struct GameState {
     camera: Camera,
     map: Map,
+    selected: Option<Vec2<i32>>,
 }
 
 impl GameState {
@@ -151,13 +46,41 @@ impl GameState {
         camera.set_viewport_size(640.0, 480.0);
         camera.update();
 
-        let map = Map::from_json(ctx, "./resources/map.json");
+        // Maps authored in the Tiled editor are the default; pass "json" to
+        // load the bespoke MapData format, or "generate" for an instant
+        // procedural map that needs no authored content at all.
+        let map = match env::args().nth(1).as_deref() {
+            Some("json") => Map::from_json(ctx, "./resources/map.json"),
+            Some("generate") => Map::generate(ctx, 1, 32, 32),
+            _ => Map::from_tiled(ctx, "./resources/map.tmx"),
+        };
 
         Ok(GameState {
             camera,
             map,
+            selected: None,
         })
     }
+
+    /// Converts the current mouse position from window space to the map cell
+    /// underneath it, by undoing the camera transform and then the isometric
+    /// projection, clamping the result to the map's bounds.
+    fn mouse_to_tile(&self, ctx: &Context) -> Vec2<i32> {
+        let mouse_position = input::get_mouse_position(ctx);
+        let inverse_camera = self.camera.as_matrix().inverted();
+        let world_position = inverse_camera * Vec4::new(mouse_position.x, mouse_position.y, 0.0, 1.0);
+
+        // isometric_to_cartesian is the exact inverse of the
+        // cartesian_to_isometric(Vec2::new(col * 32, row * 32)) used to place
+        // tiles, so it must run on the raw world position; only its result
+        // (still scaled by the 32px grid step) needs dividing down to a cell.
+        let cartesian = isometric_to_cartesian(Vec2::new(world_position.x, world_position.y));
+        let cell = Vec2::new(cartesian.x / 32, cartesian.y / 32);
+
+        let max_width = self.map.layers.iter().map(|layer| layer.width).max().unwrap_or(1) as i32;
+        let max_height = self.map.layers.iter().map(|layer| layer.height).max().unwrap_or(1) as i32;
+        Vec2::new(cell.x.clamp(0, max_width - 1), cell.y.clamp(0, max_height - 1))
+    }
 }
 
 impl State for GameState {
@@ -165,23 +88,44 @@ impl State for GameState {
         graphics::clear(ctx, Color::rgb(0.769, 0.812, 0.631));
         graphics::set_transform_matrix(ctx, self.camera.as_matrix());
 
-        for row in 0..6 {
-            for col in 0..6 {
-                let x = (col * 32) as i32;
-                let y = (row * 32) as i32;
-                let tile_index = self.map.map[row][col];
-                let tile = &self.map.tiles[&tile_index];
-                tile.draw(ctx, x, y);
+        let layer_count = self.map.layers.len() as i32;
+        let mut drawables = Vec::new();
+        for (layer_index, layer) in self.map.layers.iter().enumerate() {
+            for row in 0..layer.height {
+                for col in 0..layer.width {
+                    let tile_index = layer.tile_at(col, row);
+                    if let Some(tile) = self.map.tiles.get(&tile_index) {
+                        let depth = (col as i32 + row as i32) * layer_count + layer_index as i32;
+                        drawables.push(Drawable {
+                            tile,
+                            x: (col * 32) as i32,
+                            y: (row * 32) as i32,
+                            depth,
+                        });
+                    }
+                }
             }
         }
 
+        drawables.sort_by_key(|drawable| drawable.depth);
+
+        for drawable in drawables {
+            drawable.tile.draw(ctx, drawable.x, drawable.y);
+        }
+
         Ok(())
     }
 
-    fn event(&mut self, _: &mut Context, event: Event) -> tetra::Result {
-        if let Event::Resized { width, height } = event {
-            self.camera.set_viewport_size(width as f32, height as f32);
-            self.camera.update();
+    fn event(&mut self, ctx: &mut Context, event: Event) -> tetra::Result {
+        match event {
+            Event::Resized { width, height } => {
+                self.camera.set_viewport_size(width as f32, height as f32);
+                self.camera.update();
+            }
+            Event::MouseButtonPressed { button: MouseButton::Left } => {
+                self.selected = Some(self.mouse_to_tile(ctx));
+            }
+            _ => {}
         }
 
         Ok(())
@@ -194,4 +138,4 @@ fn main() -> tetra::Result {
         .quit_on_escape(true)
         .build()?
         .run(GameState::new)
-}
\ No newline at end of file
+}
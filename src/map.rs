@@ -0,0 +1,300 @@
+use tetra::graphics::{self, Texture, Rectangle, DrawParams};
+use tetra::math::Vec2;
+use tetra::Context;
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+
+use std::fs::File;
+use std::io::Read;
+
+use crate::ISO_WIDTH;
+use crate::ISO_HEIGHT;
+use crate::cartesian_to_isometric;
+use crate::atlas::Atlas;
+
+#[derive(Serialize, Deserialize)]
+struct MapData {
+    image: String,
+    tiles: HashMap<i32, TileData>,
+    width: usize,
+    height: usize,
+    layers: Vec<Vec<i32>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Rectangle")]
+struct RectangleDef {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TileData {
+    #[serde(with = "RectangleDef")]
+    clip: Rectangle,
+    origin: Point,
+}
+
+pub struct Tile {
+    texture: Texture,
+    clip: Rectangle,
+    origin: Vec2<f32>,
+}
+
+impl Tile {
+    pub fn draw(&self, ctx: &mut Context, x: i32, y: i32) {
+        let position = cartesian_to_isometric(Vec2::new(x, y));
+        graphics::draw(
+            ctx,
+            &self.texture,
+            DrawParams::new()
+                .position(position)
+                .origin(self.origin)
+                .clip(self.clip),
+        );
+    }
+}
+
+/// A single tile grid within a `Map`, e.g. ground, decoration or objects.
+/// Tiles of all layers are depth-sorted by cell; within one cell, layers are
+/// drawn bottom-to-top so later layers composite over earlier ones.
+pub struct Layer {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<i32>,
+}
+
+impl Layer {
+    pub fn tile_at(&self, x: usize, y: usize) -> i32 {
+        self.tiles[y * self.width + x]
+    }
+}
+
+pub struct Map {
+    pub tiles: HashMap<i32, Tile>,
+    pub layers: Vec<Layer>,
+}
+
+impl Map {
+    pub fn from_json(ctx: &mut Context, filename: &str) -> Self {
+        let map_json = read_file(filename);
+        let map_data: MapData = serde_json::from_str(&map_json).unwrap();
+        let texture = Texture::new(ctx, map_data.image).unwrap();
+
+        let tiles = map_data.tiles.into_iter()
+            .map(|(id, tile_data)| (id, Tile {
+                texture: texture.clone(),
+                clip: tile_data.clip,
+                origin: Vec2::new(tile_data.origin.x as f32, tile_data.origin.y as f32),
+            }))
+            .collect::<HashMap<_, _>>();
+
+        let layers = map_data.layers.into_iter()
+            .map(|tiles| {
+                assert_eq!(
+                    tiles.len(), map_data.width * map_data.height,
+                    "layer has {} tiles, expected width * height = {}",
+                    tiles.len(), map_data.width * map_data.height,
+                );
+                Layer { width: map_data.width, height: map_data.height, tiles }
+            })
+            .collect();
+
+        Self {
+            layers,
+            tiles,
+        }
+    }
+
+    pub fn from_tiled(ctx: &mut Context, filename: &str) -> Self {
+        let mut loader = tiled::Loader::new();
+        let tiled_map = loader.load_tmx_map(filename).unwrap();
+
+        let mut atlas = Atlas::new();
+        let mut tile_rects = Vec::new();
+        for tileset in tiled_map.tilesets() {
+            let image = tileset.image.as_ref().expect("tileset must use a single image");
+            let decoded = image::open(&image.source).unwrap().to_rgba8();
+            let atlas_rect = atlas.insert(&decoded);
+
+            let tile_width = tileset.tile_width as f32;
+            let tile_height = tileset.tile_height as f32;
+            let margin = tileset.margin as f32;
+            let spacing = tileset.spacing as f32;
+            let columns = tileset.columns as i32;
+
+            for tile_index in 0..tileset.tilecount {
+                let col = tile_index as i32 % columns;
+                let row = tile_index as i32 / columns;
+                let x = atlas_rect.x + margin + col as f32 * (tile_width + spacing);
+                let y = atlas_rect.y + margin + row as f32 * (tile_height + spacing);
+                let gid = (tileset.first_gid + tile_index) as i32;
+
+                tile_rects.push((gid, Rectangle::new(x, y, tile_width, tile_height)));
+            }
+        }
+
+        let texture = atlas.build(ctx).unwrap();
+        let tiles = tile_rects.into_iter()
+            .map(|(gid, clip)| (gid, Tile {
+                texture: texture.clone(),
+                clip,
+                origin: Vec2::new(0.0, 0.0),
+            }))
+            .collect();
+
+        let width = tiled_map.width as usize;
+        let height = tiled_map.height as usize;
+        let layers = tiled_map.layers()
+            .filter_map(|layer| match layer.layer_type() {
+                tiled::LayerType::Tiles(tile_layer) => {
+                    let mut data = vec![0; width * height];
+                    for y in 0..height {
+                        for x in 0..width {
+                            if let Some(tile) = tile_layer.get_tile(x as i32, y as i32) {
+                                data[y * width + x] = (tile.tileset().first_gid + tile.id()) as i32;
+                            }
+                        }
+                    }
+                    Some(Layer { width, height, tiles: data })
+                }
+                _ => None,
+            })
+            .collect();
+
+        Self { tiles, layers }
+    }
+
+    pub fn generate(ctx: &mut Context, seed: u32, width: usize, height: usize) -> Self {
+        let mut rng = XorShift32::new(seed);
+        let grid_size = width.max(height).next_power_of_two() + 1;
+        let heightfield = diamond_square(&mut rng, grid_size);
+
+        let mut tiles = HashMap::new();
+        tiles.insert(0, solid_tile(ctx, [64, 107, 196, 255]));
+        tiles.insert(1, solid_tile(ctx, [237, 214, 162, 255]));
+        tiles.insert(2, solid_tile(ctx, [111, 173, 90, 255]));
+        tiles.insert(3, solid_tile(ctx, [120, 120, 120, 255]));
+
+        let mut data = vec![0; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let value = heightfield[y * grid_size + x];
+                data[y * width + x] = match value {
+                    v if v < -0.2 => 0,
+                    v if v < 0.0 => 1,
+                    v if v < 0.4 => 2,
+                    _ => 3,
+                };
+            }
+        }
+
+        Self {
+            tiles,
+            layers: vec![Layer { width, height, tiles: data }],
+        }
+    }
+}
+
+struct XorShift32 {
+    state: u32,
+}
+
+impl XorShift32 {
+    fn new(seed: u32) -> Self {
+        Self { state: if seed == 0 { 1 } else { seed } }
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+
+        (x as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+// size must be 2^n + 1 so each subdivision step has an exact midpoint.
+fn diamond_square(rng: &mut XorShift32, size: usize) -> Vec<f32> {
+    let idx = |x: usize, y: usize| y * size + x;
+    let mut grid = vec![0.0; size * size];
+
+    grid[idx(0, 0)] = rng.next_f32();
+    grid[idx(size - 1, 0)] = rng.next_f32();
+    grid[idx(0, size - 1)] = rng.next_f32();
+    grid[idx(size - 1, size - 1)] = rng.next_f32();
+
+    let mut step = size - 1;
+    let mut scale = 1.0;
+    while step > 1 {
+        let half = step / 2;
+
+        let mut y = half;
+        while y < size {
+            let mut x = half;
+            while x < size {
+                let average = (grid[idx(x - half, y - half)]
+                    + grid[idx(x + half, y - half)]
+                    + grid[idx(x - half, y + half)]
+                    + grid[idx(x + half, y + half)]) / 4.0;
+                grid[idx(x, y)] = average + rng.next_f32() * scale;
+                x += step;
+            }
+            y += step;
+        }
+
+        let mut y = 0;
+        while y < size {
+            let mut x = (y + half) % step;
+            while x < size {
+                let mut sum = 0.0;
+                let mut count = 0.0;
+                if x >= half { sum += grid[idx(x - half, y)]; count += 1.0; }
+                if x + half < size { sum += grid[idx(x + half, y)]; count += 1.0; }
+                if y >= half { sum += grid[idx(x, y - half)]; count += 1.0; }
+                if y + half < size { sum += grid[idx(x, y + half)]; count += 1.0; }
+                grid[idx(x, y)] = sum / count + rng.next_f32() * scale;
+                x += step;
+            }
+            y += step;
+        }
+
+        step = half;
+        scale *= 0.5;
+    }
+
+    grid
+}
+
+fn solid_tile(ctx: &mut Context, color: [u8; 4]) -> Tile {
+    let size = ISO_WIDTH as i32;
+    let mut pixels = Vec::with_capacity((size * size) as usize * 4);
+    for _ in 0..(size * size) {
+        pixels.extend_from_slice(&color);
+    }
+
+    Tile {
+        texture: Texture::from_rgba(ctx, size, size, &pixels).unwrap(),
+        clip: Rectangle::new(0.0, 0.0, ISO_WIDTH, ISO_HEIGHT),
+        origin: Vec2::new(0.0, 0.0),
+    }
+}
+
+fn read_file(filepath: &str) -> String {
+    let mut file = File::open(filepath)
+        .expect("could not open file");
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+
+    contents
+}